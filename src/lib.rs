@@ -4,23 +4,26 @@
 //! you need to be able to create a Luminance surface after the window and event loop have already
 //! been created.
 //!
-//! This crate currently supports creating a Luminance surface from a winit window, but could also
-//! be easily extended to allow you to create surfaces from a [raw window handle][rwh]. Open an
-//! issue if you have that use case!
+//! This crate supports creating a Luminance surface from a winit window, or from a bare
+//! [raw window handle][rwh] for windowing layers that don't use winit.
 //!
-//! [rwh]: https://docs.rs/raw-window-handle/0.3.3/raw_window_handle/
+//! [rwh]: https://docs.rs/raw-window-handle/
+
+use std::mem::ManuallyDrop;
 
 use euclid::Size2D;
 use luminance::{
     context::GraphicsContext,
     framebuffer::{Framebuffer, FramebufferError},
-    texture::Dim2,
+    pixel::NormRGBA8UI,
+    texture::{Dim2, Texture, TextureError},
 };
 pub use luminance_glow::ShaderVersion;
 use luminance_glow::{Context as GlowContext, Glow, StateQueryError};
+pub use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
 use surfman::{
     Connection, Context, ContextAttributeFlags, ContextAttributes, Device, GLVersion,
-    SurfaceAccess, SurfaceType,
+    NativeContext, NativeWidget, SurfaceAccess, SurfaceTexture, SurfaceType,
 };
 use winit::window::Window;
 
@@ -34,12 +37,39 @@ pub enum SurfmanError {
     GlError(#[from] StateQueryError),
     #[error("Framebuffer error: {0}")]
     FramebufferError(#[from] FramebufferError),
+    #[error("Texture error: {0}")]
+    TextureError(#[from] TextureError),
+}
+
+/// The target a [`SurfmanSurface`] is created against
+///
+/// This mirrors the unified surface-target approach used by other platform crates (e.g. wgpu's
+/// `SurfaceTarget`): it lets [`SurfmanSurface::new`] accept either a winit window, or a bare
+/// window/display handle for windowing layers that don't use winit.
+pub enum SurfaceTarget<'a> {
+    /// Create the surface from a winit window
+    Winit(&'a Window),
+    /// Create the surface from a raw window/display handle pair
+    ///
+    /// Because there is no event loop to query the window size from, the initial size must be
+    /// provided explicitly.
+    RawHandle {
+        window: RawWindowHandle,
+        display: RawDisplayHandle,
+        size: [u32; 2],
+    },
 }
 
 pub struct SurfmanSurface {
     backend: Glow,
     device: Device,
     context: Context,
+    // Offscreen (`SurfaceType::Generic`) surfaces have nothing to present to a screen, so
+    // `swap_buffers` is a no-op for them
+    offscreen: bool,
+    // Whether `context` was created by this crate. A context wrapped from an external native
+    // context (see `from_native_context`) is owned by the caller, so `Drop` must leave it alone.
+    owns_context: bool,
 }
 
 unsafe impl GraphicsContext for SurfmanSurface {
@@ -59,31 +89,181 @@ impl SurfmanSurface {
         window: &Window,
         shader_version: ShaderVersion,
     ) -> Result<Self, SurfmanError> {
-        // Create a connection to the graphics provider from our winit window
-        let conn = Connection::from_winit_window(&window).map_err(surface_err)?;
-        // Create a native widget to attach the visible render surface to
-        let native_widget = conn
-            .create_native_widget_from_winit_window(&window)
+        // SAFETY: a winit target never touches a raw window/display handle, so this can never
+        // violate `new`'s safety contract.
+        unsafe { Self::new(SurfaceTarget::Winit(window), shader_version) }
+    }
+
+    /// Create a surface from a raw window/display handle pair
+    ///
+    /// This is useful when you don't have a winit window to create the surface from, e.g. when
+    /// using SDL2, a custom windowing layer, or embedding into a host application.
+    ///
+    /// > ⚠️ **Warning:** Because the surfman surface does not have access to the window event loop
+    /// > you will need to manualy call [`set_size`] on the surface when the window is resized.
+    ///
+    /// # Safety
+    ///
+    /// `window` and `display` must be valid handles, and must remain valid for as long as the
+    /// returned [`SurfmanSurface`] is alive, per the safety contract of the `raw-window-handle`
+    /// crate. The caller is responsible for keeping the window/display they were obtained from
+    /// alive for that whole duration.
+    pub unsafe fn from_raw_window_handle(
+        window: RawWindowHandle,
+        display: RawDisplayHandle,
+        size: [u32; 2],
+        shader_version: ShaderVersion,
+    ) -> Result<Self, SurfmanError> {
+        Self::new(
+            SurfaceTarget::RawHandle {
+                window,
+                display,
+                size,
+            },
+            shader_version,
+        )
+    }
+
+    /// Create a surface from a [`SurfaceTarget`]
+    ///
+    /// This is the entry point that both [`from_winit_window`] and [`from_raw_window_handle`]
+    /// funnel through.
+    ///
+    /// [`from_winit_window`]: Self::from_winit_window
+    /// [`from_raw_window_handle`]: Self::from_raw_window_handle
+    ///
+    /// # Safety
+    ///
+    /// If `target` is [`SurfaceTarget::RawHandle`], its window/display handles must be valid and
+    /// must remain valid for as long as the returned [`SurfmanSurface`] is alive. This is
+    /// trivially upheld for [`SurfaceTarget::Winit`], which borrows a live window.
+    pub unsafe fn new(target: SurfaceTarget, shader_version: ShaderVersion) -> Result<Self, SurfmanError> {
+        // Create a connection to the graphics provider, and a native widget to attach the
+        // visible render surface to, based on the requested target
+        let (conn, native_widget) = surface_target_native_widget(target)?;
+
+        // Define the surface type for our graphics surface ( a surface based on a native widget, i.e. not an offscreen surface )
+        let surface_type = SurfaceType::Widget { native_widget };
+
+        Self::from_connection(
+            conn,
+            surface_type,
+            Self::default_context_attributes(),
+            shader_version,
+        )
+    }
+
+    /// Create an offscreen, windowless surface
+    ///
+    /// This is useful for server-side rendering, CI image-diff tests, and thumbnail generation,
+    /// where there is no window to attach a surface to. Use [`read_back`][Self::read_back] to
+    /// retrieve the rendered pixels once you're done drawing.
+    pub fn offscreen(size: [u32; 2], shader_version: ShaderVersion) -> Result<Self, SurfmanError> {
+        // Create a connection to the graphics provider without needing a window to derive it from
+        let conn = Connection::new().map_err(surface_err)?;
+        // Define the surface type for our graphics surface ( an offscreen surface with no
+        // attached window )
+        let surface_type = SurfaceType::Generic {
+            size: Size2D::new(size[0] as i32, size[1] as i32),
+        };
+
+        Self::from_connection(
+            conn,
+            surface_type,
+            Self::default_context_attributes(),
+            shader_version,
+        )
+    }
+
+    /// Wrap an externally created native GL context (EGL/GLX/WGL/CGL)
+    ///
+    /// This is for embedding into a host application that already owns a GL context (a plugin
+    /// host, a browser embedder, an existing engine) and wants to hand it to Luminance rather
+    /// than have this crate create its own. The widget surface is attached to the wrapped
+    /// context and made current, same as the other constructors. Because this crate did not
+    /// create `native_context`, [`Drop`] will not destroy it.
+    ///
+    /// # Safety
+    ///
+    /// `native_context` must be a valid, currently-usable native GL context compatible with
+    /// `connection`, and must not be in use (current or otherwise) on another thread for the
+    /// lifetime of the returned [`SurfmanSurface`].
+    pub unsafe fn from_native_context(
+        connection: Connection,
+        native_context: NativeContext,
+        native_widget: NativeWidget,
+        shader_version: ShaderVersion,
+    ) -> Result<Self, SurfmanError> {
+        let adapter = connection.create_hardware_adapter().map_err(surface_err)?;
+        let mut device = connection.create_device(&adapter).map_err(surface_err)?;
+
+        let mut context = device
+            .create_context_from_native_context(native_context)
             .map_err(surface_err)?;
-        // Create a hardware adapter that we can used to create graphics devices from
-        let adapter = conn.create_hardware_adapter().map_err(surface_err)?;
-        // Create a graphics device using our hardware adapter
-        let mut device = conn.create_device(&adapter).map_err(surface_err)?;
 
-        // Define the attributes for our OpenGL context
-        let context_attributes = ContextAttributes {
+        let surface = device
+            .create_surface(
+                &context,
+                SurfaceAccess::GPUCPU,
+                SurfaceType::Widget { native_widget },
+            )
+            .map_err(surface_err)?;
+        device
+            .bind_surface_to_context(&mut context, surface)
+            .map_err(|(e, _)| surface_err(e))?;
+        device.make_context_current(&context).map_err(surface_err)?;
+
+        // Get a pointer to the OpenGL functions
+        // SAFETY: `context` was just made current above, so `get_proc_address` resolves
+        // against the context this loader function will be used with.
+        let gl = unsafe {
+            GlowContext::from_loader_function(
+                |s| device.get_proc_address(&context, s) as *const _,
+                shader_version,
+            )
+        };
+        let backend = Glow::from_context(gl)?;
+
+        Ok(SurfmanSurface {
+            backend,
+            device,
+            context,
+            offscreen: false,
+            owns_context: false,
+        })
+    }
+
+    // The context attributes used by `from_winit_window`/`from_raw_window_handle`/`offscreen`:
+    // GL 3.3 core profile with alpha, depth and stencil. Use [`SurfmanSurfaceBuilder`] to
+    // customize these.
+    fn default_context_attributes() -> ContextAttributes {
+        ContextAttributes {
             version: GLVersion::new(3, 3),
             flags: ContextAttributeFlags::ALPHA
                 | ContextAttributeFlags::DEPTH
                 | ContextAttributeFlags::STENCIL,
-        };
+        }
+    }
+
+    // Shared by every constructor: create a hardware adapter, device and OpenGL context, create
+    // and bind a surface of the given type, and load the Glow loader from it
+    fn from_connection(
+        conn: Connection,
+        surface_type: SurfaceType<NativeWidget>,
+        context_attributes: ContextAttributes,
+        shader_version: ShaderVersion,
+    ) -> Result<Self, SurfmanError> {
+        let offscreen = matches!(surface_type, SurfaceType::Generic { .. });
+
+        // Create a hardware adapter that we can used to create graphics devices from
+        let adapter = conn.create_hardware_adapter().map_err(surface_err)?;
+        // Create a graphics device using our hardware adapter
+        let mut device = conn.create_device(&adapter).map_err(surface_err)?;
 
         // Create a context descriptor based on our defined context attributes
         let context_descriptor = device
             .create_context_descriptor(&context_attributes)
             .map_err(surface_err)?;
-        // Define the surface type for our graphics surface ( a surface based on a native widget, i.e. not an offscreen surface )
-        let surface_type = SurfaceType::Widget { native_widget };
         // Create an OpenGL context
         let mut context = device
             .create_context(&context_descriptor, None)
@@ -102,6 +282,8 @@ impl SurfmanSurface {
         device.make_context_current(&context).map_err(surface_err)?;
 
         // Get a pointer to the OpenGL functions
+        // SAFETY: `context` was just made current above, so `get_proc_address` resolves
+        // against the context this loader function will be used with.
         let gl = unsafe {
             GlowContext::from_loader_function(
                 |s| device.get_proc_address(&context, s) as *const _,
@@ -115,6 +297,8 @@ impl SurfmanSurface {
             backend,
             device,
             context,
+            offscreen,
+            owns_context: true,
         })
     }
 
@@ -138,7 +322,15 @@ impl SurfmanSurface {
     }
 
     /// Swap the front and back buffers
+    ///
+    /// For an [`offscreen`][Self::offscreen] surface there is no window to present to, so this is
+    /// a no-op: [`read_back`][Self::read_back] maps the surface itself, which synchronizes with
+    /// any pending GL commands, so there's nothing for this call to do or wait on.
     pub fn swap_buffers(&mut self) -> Result<(), SurfmanError> {
+        if self.offscreen {
+            return Ok(());
+        }
+
         let mut surface = self
             .device
             .unbind_surface_from_context(&mut self.context)
@@ -174,16 +366,380 @@ impl SurfmanSurface {
 
         Ok(())
     }
+
+    /// Read back the surface's pixels as a tightly-packed, top-left-origin RGBA8 buffer
+    ///
+    /// This is the standard pattern used by surfman-based renderers to grab a frame: the surface
+    /// is unbound, mapped via the device's surface-data-access path, copied out row by row
+    /// (accounting for the surface's stride, which may be wider than `width * 4`), flipped
+    /// vertically since GL surfaces are bottom-left origin, then rebound.
+    pub fn read_back(&mut self) -> Result<Vec<u8>, SurfmanError> {
+        let mut buf = Vec::new();
+        self.read_back_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Like [`read_back`][Self::read_back], but writes into a caller-provided buffer instead of
+    /// allocating a new one
+    ///
+    /// `buf` is resized to fit the surface's pixels.
+    pub fn read_back_into(&mut self, buf: &mut Vec<u8>) -> Result<(), SurfmanError> {
+        let mut surface = self
+            .device
+            .unbind_surface_from_context(&mut self.context)
+            .map_err(surface_err)?
+            .unwrap();
+
+        let surface_info = self.device.surface_info(&surface);
+        let width = surface_info.size.width as usize;
+        let height = surface_info.size.height as usize;
+
+        let result = (|| {
+            let data_guard = self
+                .device
+                .lock_surface_data(&mut surface)
+                .map_err(surface_err)?;
+            let stride = data_guard.stride();
+            let data = data_guard.data();
+
+            buf.clear();
+            buf.resize(width * height * 4, 0);
+            // Flip vertically: row 0 in the mapped surface is the bottom of the image
+            for row in 0..height {
+                let src_start = row * stride;
+                let src_row = &data[src_start..src_start + width * 4];
+                let dst_row = height - 1 - row;
+                buf[dst_row * width * 4..(dst_row + 1) * width * 4].copy_from_slice(src_row);
+            }
+
+            Ok(())
+        })();
+
+        self.device
+            .bind_surface_to_context(&mut self.context, surface)
+            .map_err(|(e, _)| surface_err(e))?;
+
+        result
+    }
+
+    /// Detach this surface and convert it into a GL texture that can be sampled from another
+    /// context
+    ///
+    /// This enables a multi-surface workflow: render a scene into one offscreen
+    /// [`SurfmanSurface`], then sample its contents as a regular Luminance texture while
+    /// compositing or post-processing in another surface's context. The original surface is
+    /// restored when the returned [`SurfmanSurfaceTexture`] is dropped (or turned back with
+    /// [`into_surface`][SurfmanSurfaceTexture::into_surface]), so `swap_buffers`/`back_buffer`
+    /// keep working afterwards.
+    pub fn into_surface_texture(&mut self) -> Result<SurfmanSurfaceTexture<'_>, SurfmanError> {
+        let surface = self
+            .device
+            .unbind_surface_from_context(&mut self.context)
+            .map_err(surface_err)?
+            .unwrap();
+
+        let surface_info = self.device.surface_info(&surface);
+        let size = [
+            surface_info.size.width as u32,
+            surface_info.size.height as u32,
+        ];
+
+        let surface_texture = self
+            .device
+            .create_surface_texture(&mut self.context, surface)
+            .map_err(|(e, _)| surface_err(e))?;
+        let raw_texture = self.device.surface_texture_object(&surface_texture);
+
+        // SAFETY: `raw_texture` was just created by `create_surface_texture` above, names a
+        // valid RGBA8 2D texture of `size`, and this surface's GL context is current.
+        let texture = unsafe { luminance_glow::import_texture(self, raw_texture, size) }?;
+
+        Ok(SurfmanSurfaceTexture {
+            surface: self,
+            surface_texture: ManuallyDrop::new(surface_texture),
+            // The GL texture object is owned by `surface_texture` (surfman reclaims it in
+            // `destroy_surface_texture`), not by this `Texture` wrapper, so its destructor must
+            // never run or we'd delete the same GL name twice.
+            texture: ManuallyDrop::new(texture),
+        })
+    }
+
+    // Recover the surface behind a `SurfaceTexture` and rebind it to this context. Used to
+    // restore a surface detached by `into_surface_texture`.
+    fn restore_surface_texture(&mut self, surface_texture: SurfaceTexture) -> Result<(), SurfmanError> {
+        let surface = self
+            .device
+            .destroy_surface_texture(&mut self.context, surface_texture)
+            .map_err(|(e, _)| surface_err(e))?;
+        self.device
+            .bind_surface_to_context(&mut self.context, surface)
+            .map_err(|(e, _)| surface_err(e))?;
+
+        Ok(())
+    }
+}
+
+/// A builder for configuring the OpenGL context attributes of a [`SurfmanSurface`]
+///
+/// The convenience constructors on [`SurfmanSurface`] (e.g. [`from_winit_window`][
+/// SurfmanSurface::from_winit_window]) always request a GL 3.3 core profile context with alpha,
+/// depth and stencil. Use this builder when you need something else, e.g. a depth-only context
+/// or an older/compatibility profile for embedded or legacy GL.
+pub struct SurfmanSurfaceBuilder {
+    version: GLVersion,
+    compatibility_profile: bool,
+    alpha: bool,
+    depth: bool,
+    stencil: bool,
+}
+
+impl Default for SurfmanSurfaceBuilder {
+    fn default() -> Self {
+        SurfmanSurfaceBuilder {
+            version: GLVersion::new(3, 3),
+            compatibility_profile: false,
+            alpha: true,
+            depth: true,
+            stencil: true,
+        }
+    }
+}
+
+impl SurfmanSurfaceBuilder {
+    /// Start building a [`SurfmanSurface`] with the crate's default context attributes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request a specific OpenGL version
+    pub fn gl_version(mut self, major: u8, minor: u8) -> Self {
+        self.version = GLVersion::new(major, minor);
+        self
+    }
+
+    /// Request a compatibility profile instead of a core profile
+    pub fn compatibility_profile(mut self, compatibility_profile: bool) -> Self {
+        self.compatibility_profile = compatibility_profile;
+        self
+    }
+
+    /// Whether the context should have an alpha channel
+    pub fn alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Whether the context should have a depth buffer
+    pub fn depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Whether the context should have a stencil buffer
+    pub fn stencil(mut self, stencil: bool) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
+    fn context_attributes(&self) -> ContextAttributes {
+        let mut flags = ContextAttributeFlags::empty();
+        flags.set(ContextAttributeFlags::ALPHA, self.alpha);
+        flags.set(ContextAttributeFlags::DEPTH, self.depth);
+        flags.set(ContextAttributeFlags::STENCIL, self.stencil);
+        flags.set(
+            ContextAttributeFlags::COMPATIBILITY_PROFILE,
+            self.compatibility_profile,
+        );
+
+        ContextAttributes {
+            version: self.version,
+            flags,
+        }
+    }
+
+    /// Finish building, creating a surface from a winit window
+    pub fn build_from_winit_window(
+        self,
+        window: &Window,
+        shader_version: ShaderVersion,
+    ) -> Result<SurfmanSurface, SurfmanError> {
+        // SAFETY: a winit target never touches a raw window/display handle, so this can never
+        // violate `build`'s safety contract.
+        unsafe { self.build(SurfaceTarget::Winit(window), shader_version) }
+    }
+
+    /// Finish building, creating a surface from a raw window/display handle pair
+    ///
+    /// # Safety
+    ///
+    /// `window` and `display` must be valid handles, and must remain valid for as long as the
+    /// returned [`SurfmanSurface`] is alive, per the safety contract of the `raw-window-handle`
+    /// crate. The caller is responsible for keeping the window/display they were obtained from
+    /// alive for that whole duration.
+    pub unsafe fn build_from_raw_window_handle(
+        self,
+        window: RawWindowHandle,
+        display: RawDisplayHandle,
+        size: [u32; 2],
+        shader_version: ShaderVersion,
+    ) -> Result<SurfmanSurface, SurfmanError> {
+        self.build(
+            SurfaceTarget::RawHandle {
+                window,
+                display,
+                size,
+            },
+            shader_version,
+        )
+    }
+
+    // SAFETY: if `target` is `SurfaceTarget::RawHandle`, its window/display handles must be
+    // valid and remain valid for as long as the resulting `SurfmanSurface` is alive. See
+    // `surface_target_native_widget`.
+    unsafe fn build(
+        self,
+        target: SurfaceTarget,
+        shader_version: ShaderVersion,
+    ) -> Result<SurfmanSurface, SurfmanError> {
+        let (conn, native_widget) = surface_target_native_widget(target)?;
+        let surface_type = SurfaceType::Widget { native_widget };
+
+        SurfmanSurface::from_connection(
+            conn,
+            surface_type,
+            self.context_attributes(),
+            shader_version,
+        )
+    }
+
+    /// Finish building, creating an offscreen, windowless surface
+    pub fn build_offscreen(
+        self,
+        size: [u32; 2],
+        shader_version: ShaderVersion,
+    ) -> Result<SurfmanSurface, SurfmanError> {
+        let conn = Connection::new().map_err(surface_err)?;
+        let surface_type = SurfaceType::Generic {
+            size: Size2D::new(size[0] as i32, size[1] as i32),
+        };
+
+        SurfmanSurface::from_connection(
+            conn,
+            surface_type,
+            self.context_attributes(),
+            shader_version,
+        )
+    }
 }
 
 impl Drop for SurfmanSurface {
     fn drop(&mut self) {
+        if !self.owns_context {
+            // We don't own the GL context, so leave it alone. We do however own the surface
+            // bound to it (created by `create_surface` in `from_native_context`), so reclaim
+            // that ourselves before returning.
+            if let Ok(Some(mut surface)) = self.device.unbind_surface_from_context(&mut self.context)
+            {
+                let _ = self.device.destroy_surface(&mut self.context, &mut surface);
+            }
+            return;
+        }
+
         self.device
             .destroy_context(&mut self.context)
             .unwrap_or_else(|e| eprintln!("Error destroying surfman context: {:?}", e));
     }
 }
 
+/// A [`SurfmanSurface`] detached and wrapped as a sampleable GL texture
+///
+/// Returned by [`SurfmanSurface::into_surface_texture`]. Dereferences to the underlying
+/// `Texture<Glow, Dim2, NormRGBA8UI>` so it can be bound into a pipeline like any other Luminance
+/// texture. Dropping this value (or calling [`into_surface`][Self::into_surface]) detaches the
+/// texture and reattaches the original surface to its context.
+pub struct SurfmanSurfaceTexture<'a> {
+    surface: &'a mut SurfmanSurface,
+    surface_texture: ManuallyDrop<SurfaceTexture>,
+    // The GL texture object named here is owned by `surface_texture`, not by this wrapper, so
+    // its destructor is never allowed to run (see `Drop` below) — otherwise we'd delete the same
+    // GL name twice, once here and once when surfman reclaims the surface.
+    texture: ManuallyDrop<Texture<Glow, Dim2, NormRGBA8UI>>,
+}
+
+impl<'a> std::ops::Deref for SurfmanSurfaceTexture<'a> {
+    type Target = Texture<Glow, Dim2, NormRGBA8UI>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.texture
+    }
+}
+
+impl<'a> std::ops::DerefMut for SurfmanSurfaceTexture<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.texture
+    }
+}
+
+impl<'a> SurfmanSurfaceTexture<'a> {
+    /// Detach the GL texture and reattach the recovered surface to its original context
+    pub fn into_surface(self) -> Result<(), SurfmanError> {
+        // SAFETY: `this` is never used again, and its `Drop` impl is suppressed by the
+        // `ManuallyDrop` wrapper above, so neither `surface_texture` nor `texture` is
+        // double-destroyed.
+        let mut this = ManuallyDrop::new(self);
+        let surface_texture = unsafe { ManuallyDrop::take(&mut this.surface_texture) };
+        this.surface.restore_surface_texture(surface_texture)
+    }
+}
+
+impl<'a> Drop for SurfmanSurfaceTexture<'a> {
+    fn drop(&mut self) {
+        // SAFETY: this is the only place `surface_texture` is taken out of `self` via `Drop`.
+        // `texture` is intentionally left inside its `ManuallyDrop` and never dropped: the GL
+        // object it names is freed below, by surfman, when the surface is reclaimed.
+        let surface_texture = unsafe { ManuallyDrop::take(&mut self.surface_texture) };
+        self.surface
+            .restore_surface_texture(surface_texture)
+            .unwrap_or_else(|e| eprintln!("Error restoring surfman surface: {:?}", e));
+    }
+}
+
+// Resolve a `SurfaceTarget` into the connection and native widget used to create a windowed
+// surface for it. Shared by `SurfmanSurface::new` and `SurfmanSurfaceBuilder::build`.
+//
+// SAFETY: if `target` is `SurfaceTarget::RawHandle`, its window/display handles must be valid
+// and remain valid for as long as the resulting connection/native widget are used, per the
+// `raw-window-handle` safety contract. This is upheld by the `unsafe fn`s that produce a
+// `SurfaceTarget` containing raw handles (`SurfmanSurface::from_raw_window_handle`,
+// `SurfmanSurfaceBuilder::build_from_raw_window_handle`).
+unsafe fn surface_target_native_widget(
+    target: SurfaceTarget,
+) -> Result<(Connection, NativeWidget), SurfmanError> {
+    match target {
+        SurfaceTarget::Winit(window) => {
+            let conn = Connection::from_winit_window(&window).map_err(surface_err)?;
+            let native_widget = conn
+                .create_native_widget_from_winit_window(&window)
+                .map_err(surface_err)?;
+            Ok((conn, native_widget))
+        }
+        SurfaceTarget::RawHandle {
+            window,
+            display,
+            size,
+        } => {
+            let conn = Connection::from_raw_display_handle(display).map_err(surface_err)?;
+            let native_widget = conn
+                .create_native_widget_from_raw_window_handle(
+                    window,
+                    Size2D::new(size[0] as i32, size[1] as i32),
+                )
+                .map_err(surface_err)?;
+            Ok((conn, native_widget))
+        }
+    }
+}
+
 // Util to format map a surfman error to this crate's [`SurfmanError`]
 fn surface_err(e: surfman::Error) -> SurfmanError {
     SurfmanError::SurfaceError(format!("{:?}", e))